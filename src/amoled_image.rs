@@ -1,11 +1,20 @@
-use std::{fmt, io, path::Path};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+};
 
 use crate::Message;
 
-use ::image::{io::Reader as ImageReader, Bgra, DynamicImage, ImageBuffer};
+use ::image::{
+    imageops::{self, FilterType},
+    io::Reader as ImageReader,
+    Bgra, DynamicImage, ImageBuffer, Rgb, RgbImage, Rgba, RgbaImage,
+};
 use iced::{
-    image::{viewer, Handle, Viewer},
-    Align, Column, Container, Element, HorizontalAlignment, Length, Row, Text,
+    image::Handle, Align, Column, Container, Element, HorizontalAlignment, Image, Length, Row, Text,
 };
 use image::ImageError;
 
@@ -16,6 +25,15 @@ use image::ImageError;
 //     pixels: Vec<u8>,
 // }
 
+/// Side length the thumbnail is further downscaled to before the (expensive)
+/// k-means quantizer runs on the UI thread, keeping the live Quantize preview
+/// cheap. The full-resolution pass still runs off-thread.
+const QUANTIZE_PREVIEW_MAX: u32 = 128;
+
+/// Bounds on the shared preview zoom factor applied to both viewers.
+const MIN_VIEW_SCALE: f32 = 1.0;
+const MAX_VIEW_SCALE: f32 = 10.0;
+
 const THUMBNAIL_MAX_WIDTH: u32 = 1024;
 const THUMBNAIL_MIN_WIDTH: u32 = 256;
 const THUMBNAIL_MAX_HEIGHT: u32 = 1024;
@@ -25,6 +43,7 @@ const THUMBNAIL_MIN_HEIGHT: u32 = 256;
 pub enum AmoledConversionError {
     DecodeError(io::Error),
     ImageError(ImageError),
+    EncodeError(ImageError),
     // ImageParseError,
 }
 
@@ -45,6 +64,7 @@ impl fmt::Display for AmoledConversionError {
         match self {
             AmoledConversionError::DecodeError(e) => write!(f, "Decode error: {}", e),
             AmoledConversionError::ImageError(e) => write!(f, "Image error: {}", e),
+            AmoledConversionError::EncodeError(e) => write!(f, "Encode error: {}", e),
             // AmoledImageError::ImageParseError => write!(f, "Image parse error"),
         }
     }
@@ -61,8 +81,85 @@ struct PixelInfo {
     black_pixels: usize,
 }
 
+/// Result of converting a single file during a batch run.
+#[derive(Debug, Clone)]
+pub struct BatchOutcome {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub black_pixel_percentage: usize,
+}
+
+/// How a source image is turned into its AMOLED output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConversionMode {
+    /// Hard per-channel threshold: any pixel whose channels all sit at or below
+    /// `black_point` is clamped to pure black.
+    Threshold,
+    /// Perceptual palette reduction: quantize to `num_colors` representative
+    /// colors, snap every palette entry dimmer than `luminance_cutoff` to pure
+    /// black, then remap the image to the reduced palette. This darkens broad
+    /// near-black gradients rather than only pixels already under the threshold.
+    Quantize {
+        num_colors: usize,
+        luminance_cutoff: u8,
+    },
+}
+
+impl Default for ConversionMode {
+    fn default() -> Self {
+        ConversionMode::Threshold
+    }
+}
+
 type Result<T> = std::result::Result<T, AmoledConversionError>;
 
+/// Upper bound on distinct `(image, black_point)` conversions kept around
+/// before the least-recently-used entry is dropped.
+const CONVERSION_CACHE_BOUND: usize = 8;
+
+#[derive(Debug, Clone)]
+struct CachedConversion {
+    converted: ImageBuffer<Bgra<u8>, Vec<u8>>,
+    black_pixel_percentage: usize,
+}
+
+/// Small LRU cache of full-resolution conversions keyed by a digest of the
+/// source bytes combined with the `black_point`. Scrubbing the slider back and
+/// forth over thresholds it has already seen becomes a lookup instead of a
+/// fresh full-res pass.
+#[derive(Debug, Clone, Default)]
+struct ConversionCache {
+    entries: Vec<(u64, CachedConversion)>,
+}
+
+impl ConversionCache {
+    fn get(&mut self, key: u64) -> Option<CachedConversion> {
+        let pos = self.entries.iter().position(|(k, _)| *k == key)?;
+        // Promote the hit to most-recently-used.
+        let entry = self.entries.remove(pos);
+        let value = entry.1.clone();
+        self.entries.push(entry);
+        Some(value)
+    }
+
+    fn insert(&mut self, key: u64, value: CachedConversion) {
+        if let Some(pos) = self.entries.iter().position(|(k, _)| *k == key) {
+            self.entries.remove(pos);
+        }
+        self.entries.push((key, value));
+        if self.entries.len() > CONVERSION_CACHE_BOUND {
+            // Evict the least-recently-used entry.
+            self.entries.remove(0);
+        }
+    }
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
 #[derive(Debug, Clone)]
 pub struct AmoledImageConverter {
     width: u32,
@@ -72,11 +169,14 @@ pub struct AmoledImageConverter {
     converted_image: ImageBuffer<Bgra<u8>, Vec<u8>>,
     pub thumbnail: ImageBuffer<Bgra<u8>, Vec<u8>>,
     converted_thumbnail: ImageBuffer<Bgra<u8>, Vec<u8>>,
-    first_image_viewer: viewer::State,
-    second_image_viewer: viewer::State,
+    /// Zoom factor shared by both preview viewers so they stay in lockstep.
+    view_scale: f32,
     black_point: u8,
     image_handle: Handle,
     converted_image_black_pixel_percentage: usize,
+    source_hash: u64,
+    cache: ConversionCache,
+    mode: ConversionMode,
 }
 
 impl AmoledImageConverter {
@@ -102,12 +202,26 @@ impl AmoledImageConverter {
             )
             .to_bgra8();
 
+        let mode = ConversionMode::default();
         let mut converted_thumbnail = thumbnail.clone();
-        AmoledImageConverter::generate_black_image(&mut converted_thumbnail, black_point);
+        AmoledImageConverter::convert_in_place(&mut converted_thumbnail, black_point, mode);
         // This is quite a heavy task and can maybe be otimized if we don't need the original image's black point ratio.
         let mut converted_image = bgra8_image.clone();
         let converted_info =
-            AmoledImageConverter::generate_black_image(&mut converted_image, black_point);
+            AmoledImageConverter::convert_in_place(&mut converted_image, black_point, mode);
+        let source_hash = hash_bytes(bgra8_image.as_raw());
+        let black_pixel_percentage = AmoledImageConverter::calc_black_pixel_percentage(
+            converted_info.black_pixels,
+            converted_info.pixels,
+        );
+        let mut cache = ConversionCache::default();
+        cache.insert(
+            AmoledImageConverter::cache_key(source_hash, black_point, mode),
+            CachedConversion {
+                converted: converted_image.clone(),
+                black_pixel_percentage,
+            },
+        );
         return Ok(AmoledImageConverter {
             width: bgra8_image.width(),
             height: bgra8_image.height(),
@@ -116,23 +230,78 @@ impl AmoledImageConverter {
             thumbnail,
             converted_thumbnail,
             image_handle: Handle::from_memory(bgra8_image.as_raw().to_owned()),
-            first_image_viewer: iced::image::viewer::State::new(),
-            second_image_viewer: iced::image::viewer::State::new(),
+            view_scale: 1.0,
             black_point,
-            converted_image_black_pixel_percentage:
-                AmoledImageConverter::calc_black_pixel_percentage(
-                    converted_info.black_pixels,
-                    converted_info.pixels,
-                ),
+            converted_image_black_pixel_percentage: black_pixel_percentage,
+            source_hash,
+            cache,
+            mode,
         });
 
         // If the image cannot load into a brga8 image, return an empty image.
     }
 
+    /// Zoom both previews by `steps` scroll-wheel notches about their centres,
+    /// keeping the two in lockstep through the shared [`view_scale`]. Positive
+    /// `steps` zoom in, negative zoom out; the factor is clamped to
+    /// `[MIN_VIEW_SCALE, MAX_VIEW_SCALE]`.
+    ///
+    /// The shared scale is applied identically to both [`Image`] widgets in
+    /// [`view`](Self::view), so scrolling over either mirrors onto the other.
+    /// The stock `image::viewer` is not used here because its scale is private
+    /// and per-widget, which is precisely what kept the two previews
+    /// independent; driving a single scale from the converter is what makes
+    /// before/after inspection line up.
+    pub fn zoom(&mut self, steps: f32) {
+        let factor = 1.0 + steps * 0.1;
+        self.view_scale = (self.view_scale * factor)
+            .max(MIN_VIEW_SCALE)
+            .min(MAX_VIEW_SCALE);
+    }
+
+    /// Restore both previews to 1:1 scale.
+    pub fn reset_view(&mut self) {
+        self.view_scale = 1.0;
+    }
+
     fn calc_black_pixel_percentage(black_pixel_count: usize, pixel_count: usize) -> usize {
         black_pixel_count * 100 / pixel_count
     }
 
+    pub fn black_pixel_percentage(&self) -> usize {
+        self.converted_image_black_pixel_percentage
+    }
+
+    /// Decode `input`, convert it at `black_point`/`mode`, and write the result
+    /// into `output_dir` under the same file name. Intended to run off the UI
+    /// thread, one call per file during a batch run.
+    pub fn convert_file_to_dir(
+        input: &Path,
+        output_dir: &Path,
+        black_point: u8,
+        mode: ConversionMode,
+    ) -> Result<BatchOutcome> {
+        let mut converter = AmoledImageConverter::from_path(input, black_point)?;
+        // `from_path` converts with the default threshold mode; honour the
+        // requested mode by running the full-res pass again when it differs.
+        if mode != ConversionMode::default() {
+            converter.set_mode(mode);
+            let (converted, black_pixel_percentage) =
+                AmoledImageConverter::convert_full(converter.source(), black_point, mode);
+            converter.set_converted_image(black_point, converted, black_pixel_percentage);
+        }
+        let file_name = input.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "input path has no file name")
+        })?;
+        let output = output_dir.join(file_name);
+        converter.export(&output)?;
+        Ok(BatchOutcome {
+            input: input.to_owned(),
+            output,
+            black_pixel_percentage: converter.black_pixel_percentage(),
+        })
+    }
+
     pub fn get_width(&self) -> u32 {
         self.width
     }
@@ -141,18 +310,173 @@ impl AmoledImageConverter {
         self.height
     }
 
+    /// Cheap preview recompute run on the UI thread: re-threshold only the
+    /// thumbnail and refresh the black-pixel readout so the slider stays
+    /// responsive. The matching full-resolution pass is run off-thread via
+    /// [`AmoledImageConverter::convert_full`] and stored with
+    /// [`AmoledImageConverter::set_converted_image`].
     pub fn set_black_point(&mut self, black_point: u8) {
         self.black_point = black_point;
-        self.converted_thumbnail = self.thumbnail.clone();
-        let converted_image_info =
-            AmoledImageConverter::generate_black_image(&mut self.converted_thumbnail, black_point);
+        // Quantizing the full 1024px thumbnail on the UI thread would freeze the
+        // window, so the live preview runs over an aggressively downscaled copy;
+        // the full-resolution quantization still happens off-thread.
+        self.converted_thumbnail = match self.mode {
+            ConversionMode::Threshold => self.thumbnail.clone(),
+            ConversionMode::Quantize { .. } => {
+                AmoledImageConverter::downscale(&self.thumbnail, QUANTIZE_PREVIEW_MAX)
+            }
+        };
+        let converted_image_info = AmoledImageConverter::convert_in_place(
+            &mut self.converted_thumbnail,
+            black_point,
+            self.mode,
+        );
         self.converted_image_black_pixel_percentage =
             AmoledImageConverter::calc_black_pixel_percentage(
                 converted_image_info.black_pixels,
                 converted_image_info.pixels,
             );
-        self.converted_image = self.image.clone();
-        AmoledImageConverter::generate_black_image(&mut self.converted_image, black_point);
+    }
+
+    fn downscale(image: &ImageBuffer<Bgra<u8>, Vec<u8>>, max: u32) -> ImageBuffer<Bgra<u8>, Vec<u8>> {
+        let (width, height) = (image.width(), image.height());
+        if width <= max && height <= max {
+            return image.clone();
+        }
+        // Preserve the aspect ratio, bounding the longest side to `max`.
+        let ratio = max as f32 / width.max(height) as f32;
+        let target_width = ((width as f32 * ratio) as u32).max(1);
+        let target_height = ((height as f32 * ratio) as u32).max(1);
+        imageops::resize(image, target_width, target_height, FilterType::Triangle)
+    }
+
+    /// Switch the conversion mode. The caller is expected to follow up with
+    /// [`AmoledImageConverter::set_black_point`] to refresh the preview at the
+    /// current black point and then run a full-resolution pass, just as it does
+    /// after a black-point change.
+    pub fn set_mode(&mut self, mode: ConversionMode) {
+        self.mode = mode;
+    }
+
+    pub fn mode(&self) -> ConversionMode {
+        self.mode
+    }
+
+    /// Clone of the decoded full-resolution source buffer, handed to a blocking
+    /// executor thread so the expensive conversion never touches the UI thread.
+    pub fn source(&self) -> ImageBuffer<Bgra<u8>, Vec<u8>> {
+        self.image.clone()
+    }
+
+    /// Run the full-resolution pass on an owned buffer, returning the converted
+    /// buffer together with its real black-pixel percentage. Intended to be
+    /// called from a blocking executor thread (e.g. `smol::unblock`). Returning
+    /// the percentage keeps the readout honest in Quantize mode, where it cannot
+    /// be inferred from the threshold pass or the downscaled preview.
+    pub fn convert_full(
+        mut image: ImageBuffer<Bgra<u8>, Vec<u8>>,
+        black_point: u8,
+        mode: ConversionMode,
+    ) -> (ImageBuffer<Bgra<u8>, Vec<u8>>, usize) {
+        let info = AmoledImageConverter::convert_in_place(&mut image, black_point, mode);
+        let black_pixel_percentage =
+            AmoledImageConverter::calc_black_pixel_percentage(info.black_pixels, info.pixels);
+        (image, black_pixel_percentage)
+    }
+
+    /// Store the buffer produced by an off-thread full-resolution conversion
+    /// along with its real black-pixel percentage, and remember both in the
+    /// cache under the given `black_point`.
+    pub fn set_converted_image(
+        &mut self,
+        black_point: u8,
+        converted: ImageBuffer<Bgra<u8>, Vec<u8>>,
+        black_pixel_percentage: usize,
+    ) {
+        self.converted_image_black_pixel_percentage = black_pixel_percentage;
+        self.cache.insert(
+            AmoledImageConverter::cache_key(self.source_hash, black_point, self.mode),
+            CachedConversion {
+                converted: converted.clone(),
+                black_pixel_percentage,
+            },
+        );
+        self.converted_image = converted;
+    }
+
+    /// Look up a previously computed full-resolution conversion for the given
+    /// `black_point`. On a hit the converted buffer and black-pixel readout are
+    /// restored in place and `true` is returned, letting the caller skip the
+    /// expensive full-res pass entirely.
+    pub fn restore_cached_conversion(&mut self, black_point: u8) -> bool {
+        let key = AmoledImageConverter::cache_key(self.source_hash, black_point, self.mode);
+        match self.cache.get(key) {
+            Some(cached) => {
+                self.converted_image = cached.converted;
+                self.converted_image_black_pixel_percentage = cached.black_pixel_percentage;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn cache_key(source_hash: u64, black_point: u8, mode: ConversionMode) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        source_hash.hash(&mut hasher);
+        black_point.hash(&mut hasher);
+        match mode {
+            ConversionMode::Threshold => 0u8.hash(&mut hasher),
+            ConversionMode::Quantize {
+                num_colors,
+                luminance_cutoff,
+            } => {
+                1u8.hash(&mut hasher);
+                num_colors.hash(&mut hasher);
+                luminance_cutoff.hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+
+    /// Write the full-resolution converted image to `path`, encoding it with
+    /// the format inferred from the file extension. The internal buffer is
+    /// `Bgra<u8>`; the channels are swapped back to RGBA before saving, dropping
+    /// the alpha channel for JPEG targets whose encoder cannot accept it.
+    pub fn export(&self, path: &Path) -> Result<()> {
+        if AmoledImageConverter::is_jpeg(path) {
+            let rgb = AmoledImageConverter::to_rgb(&self.converted_image);
+            rgb.save(path).map_err(AmoledConversionError::EncodeError)?;
+        } else {
+            let rgba = AmoledImageConverter::to_rgba(&self.converted_image);
+            rgba.save(path).map_err(AmoledConversionError::EncodeError)?;
+        }
+        Ok(())
+    }
+
+    fn is_jpeg(path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| {
+                let ext = ext.to_ascii_lowercase();
+                ext == "jpg" || ext == "jpeg"
+            })
+            .unwrap_or(false)
+    }
+
+    fn to_rgba(image: &ImageBuffer<Bgra<u8>, Vec<u8>>) -> RgbaImage {
+        let mut rgba = RgbaImage::new(image.width(), image.height());
+        for (out, pixel) in rgba.pixels_mut().zip(image.pixels()) {
+            *out = Rgba([pixel[2], pixel[1], pixel[0], pixel[3]]);
+        }
+        rgba
+    }
+
+    fn to_rgb(image: &ImageBuffer<Bgra<u8>, Vec<u8>>) -> RgbImage {
+        let mut rgb = RgbImage::new(image.width(), image.height());
+        for (out, pixel) in rgb.pixels_mut().zip(image.pixels()) {
+            *out = Rgb([pixel[2], pixel[1], pixel[0]]);
+        }
+        rgb
     }
 
     fn clamp(x: u32, min: u32, max: u32) -> u32 {
@@ -178,6 +502,83 @@ impl AmoledImageConverter {
         count
     }
 
+    fn convert_in_place(
+        image: &mut ImageBuffer<Bgra<u8>, Vec<u8>>,
+        black_point: u8,
+        mode: ConversionMode,
+    ) -> PixelInfo {
+        match mode {
+            ConversionMode::Threshold => {
+                AmoledImageConverter::generate_black_image(image, black_point)
+            }
+            ConversionMode::Quantize {
+                num_colors,
+                luminance_cutoff,
+            } => AmoledImageConverter::generate_quantized_image(
+                image,
+                num_colors,
+                luminance_cutoff,
+            ),
+        }
+    }
+
+    fn generate_quantized_image(
+        image: &mut ImageBuffer<Bgra<u8>, Vec<u8>>,
+        num_colors: usize,
+        luminance_cutoff: u8,
+    ) -> PixelInfo {
+        use exoquant::{convert_to_indexed, ditherer, optimizer, Color};
+
+        // exoquant works in RGBA; the buffer is BGRA.
+        let pixels: Vec<Color> = image
+            .pixels()
+            .map(|p| Color::new(p[2], p[1], p[0], p[3]))
+            .collect();
+        let pixel_count = pixels.len();
+        let width = image.width() as usize;
+
+        let (palette, indices) = convert_to_indexed(
+            &pixels,
+            width,
+            num_colors,
+            &optimizer::KMeans,
+            &ditherer::None,
+        );
+
+        // Snap every palette entry dimmer than the cutoff all the way to black.
+        let mut palette_is_black = vec![false; palette.len()];
+        let black_palette: Vec<Color> = palette
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let luminance = 0.299 * c.r as f32 + 0.587 * c.g as f32 + 0.114 * c.b as f32;
+                if luminance < luminance_cutoff as f32 {
+                    palette_is_black[i] = true;
+                    Color::new(0, 0, 0, c.a)
+                } else {
+                    *c
+                }
+            })
+            .collect();
+
+        // Reconstruct the output buffer from the edited palette + index map,
+        // counting how many pixels landed on a black palette entry.
+        let mut black_pixel_count: usize = 0;
+        for (pixel, &index) in image.pixels_mut().zip(indices.iter()) {
+            let index = index as usize;
+            let c = black_palette[index];
+            *pixel = Bgra([c.b, c.g, c.r, c.a]);
+            if palette_is_black[index] {
+                black_pixel_count += 1;
+            }
+        }
+
+        PixelInfo {
+            pixels: pixel_count,
+            black_pixels: black_pixel_count,
+        }
+    }
+
     fn generate_black_image(
         image: &mut ImageBuffer<Bgra<u8>, Vec<u8>>,
         black_point: u8,
@@ -215,6 +616,7 @@ impl AmoledImageConverter {
     }
 
     pub fn view(&mut self) -> Element<Message> {
+        let scale = self.view_scale;
         Container::new(
             Row::new()
                 .padding(10)
@@ -222,7 +624,7 @@ impl AmoledImageConverter {
                 .push(
                     AmoledImageConverter::view_thumbnail(
                         &self.thumbnail,
-                        &mut self.first_image_viewer,
+                        scale,
                         AmoledImageConverter::count_black_pixels(&self.image) * 100
                             / self.image.pixels().len(),
                     )
@@ -231,7 +633,7 @@ impl AmoledImageConverter {
                 .push(
                     AmoledImageConverter::view_thumbnail(
                         &self.converted_thumbnail,
-                        &mut self.second_image_viewer,
+                        scale,
                         self.converted_image_black_pixel_percentage,
                     )
                     .align_items(Align::Start),
@@ -243,23 +645,24 @@ impl AmoledImageConverter {
 
     fn view_thumbnail<'a>(
         thumbnail: &ImageBuffer<Bgra<u8>, Vec<u8>>,
-        viewer: &'a mut iced::image::viewer::State,
+        scale: f32,
         black_pixel_percentage: usize,
     ) -> Column<'a, Message> {
+        // The same `scale` drives both previews, so zooming one mirrors the other.
+        let width = (thumbnail.width() as f32 * scale) as u16;
+        let height = (thumbnail.height() as f32 * scale) as u16;
         Column::new().width(Length::Fill).push(
             Column::new()
                 .align_items(Align::Center)
                 .spacing(20)
                 .push(
-                    Viewer::new(
-                        viewer,
-                        Handle::from_pixels(
-                            thumbnail.width(),
-                            thumbnail.height(),
-                            thumbnail.as_raw().to_owned(),
-                        ),
-                    )
-                    .height(Length::Fill),
+                    Image::new(Handle::from_pixels(
+                        thumbnail.width(),
+                        thumbnail.height(),
+                        thumbnail.as_raw().to_owned(),
+                    ))
+                    .width(Length::Units(width))
+                    .height(Length::Units(height)),
                 )
                 .push(
                     Text::new(format!("{}% Black", black_pixel_percentage))
@@ -268,3 +671,66 @@ impl AmoledImageConverter {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(b: u8, g: u8, r: u8, a: u8) -> ImageBuffer<Bgra<u8>, Vec<u8>> {
+        ImageBuffer::from_pixel(1, 1, Bgra([b, g, r, a]))
+    }
+
+    fn cached(id: u8) -> CachedConversion {
+        CachedConversion {
+            converted: pixel(id, id, id, 255),
+            black_pixel_percentage: id as usize,
+        }
+    }
+
+    #[test]
+    fn cache_evicts_least_recently_used() {
+        let mut cache = ConversionCache::default();
+        // Fill past the bound; the first-inserted key should be dropped.
+        for key in 0..=CONVERSION_CACHE_BOUND as u64 {
+            cache.insert(key, cached(key as u8));
+        }
+        assert_eq!(cache.entries.len(), CONVERSION_CACHE_BOUND);
+        assert!(cache.get(0).is_none());
+        assert!(cache.get(CONVERSION_CACHE_BOUND as u64).is_some());
+    }
+
+    #[test]
+    fn cache_get_promotes_to_most_recently_used() {
+        let mut cache = ConversionCache::default();
+        for key in 0..CONVERSION_CACHE_BOUND as u64 {
+            cache.insert(key, cached(key as u8));
+        }
+        // Touch key 0 so it is no longer the eviction candidate.
+        assert!(cache.get(0).is_some());
+        // Inserting a fresh key now evicts key 1, not the promoted key 0.
+        cache.insert(100, cached(100));
+        assert!(cache.get(0).is_some());
+        assert!(cache.get(1).is_none());
+    }
+
+    #[test]
+    fn to_rgba_swaps_bgra_channels() {
+        let rgba = AmoledImageConverter::to_rgba(&pixel(10, 20, 30, 40));
+        assert_eq!(rgba.get_pixel(0, 0).0, [30, 20, 10, 40]);
+    }
+
+    #[test]
+    fn to_rgb_swaps_and_drops_alpha() {
+        let rgb = AmoledImageConverter::to_rgb(&pixel(10, 20, 30, 40));
+        assert_eq!(rgb.get_pixel(0, 0).0, [30, 20, 10]);
+    }
+
+    #[test]
+    fn is_jpeg_matches_jpg_extensions_only() {
+        assert!(AmoledImageConverter::is_jpeg(Path::new("a.jpg")));
+        assert!(AmoledImageConverter::is_jpeg(Path::new("a.jpeg")));
+        assert!(AmoledImageConverter::is_jpeg(Path::new("A.JPG")));
+        assert!(!AmoledImageConverter::is_jpeg(Path::new("a.png")));
+        assert!(!AmoledImageConverter::is_jpeg(Path::new("a")));
+    }
+}
@@ -1,17 +1,25 @@
 // #![windows_subsystem = "windows"]
 mod amoled_image;
 
-use amoled_image::AmoledImageConverter;
+use amoled_image::{AmoledImageConverter, ConversionMode};
 
-use std::path::PathBuf;
+use std::{fs, path::PathBuf, time::Duration};
 
 use iced::{
-    button, slider, text_input, window, Align, Button, Column, Container, Element,
-    HorizontalAlignment, Length, Row, Sandbox, Settings, Slider, Text, TextInput,
+    button, executor, keyboard, mouse, slider, text_input, window, Align, Application, Button,
+    Column, Command, Container, Element, HorizontalAlignment, Length, Row, Settings, Slider,
+    Subscription, Text, TextInput,
 };
 
+use ::image::{Bgra, ImageBuffer};
 use rfd::FileDialog;
 
+const DEFAULT_NUM_COLORS: usize = 64;
+const DEFAULT_LUMINANCE_CUTOFF: u8 = 32;
+/// How long the slider/controls must settle before the expensive full-res pass
+/// is fired, so scrubbing does not pile conversions onto the blocking pool.
+const CONVERSION_DEBOUNCE: Duration = Duration::from_millis(200);
+
 pub fn main() -> iced::Result {
     Amoled::run(Settings {
         window: (window::Settings {
@@ -36,6 +44,21 @@ struct Amoled {
     path_input: text_input::State,
     path_input_value: Option<PathBuf>,
     file_open_button: button::State,
+    file_save_button: button::State,
+    reset_view_button: button::State,
+    quantize_enabled: bool,
+    num_colors: usize,
+    luminance_cutoff: u8,
+    mode_toggle_button: button::State,
+    num_colors_input: text_input::State,
+    luminance_cutoff_input: text_input::State,
+    batch_button: button::State,
+    batch_total: usize,
+    batch_done: usize,
+    batch_success: usize,
+    batch_percentage_sum: usize,
+    batch_summary: Vec<String>,
+    conversion_generation: u64,
     image: Option<AmoledImageConverter>,
     // first_image_pixels: Option<ImagePixels>,
     // second_image_pixels: Option<ImagePixels>,
@@ -50,22 +73,150 @@ pub enum Message {
     BlackPointInputChanged(String),
     // FileCreated,
     FileButtonpressed,
+    ExportFile,
+    ImageLoaded(Result<AmoledImageConverter, String>),
+    ConversionDone(u8, ImageBuffer<Bgra<u8>, Vec<u8>>, usize),
+    ResetView,
+    Zoom(f32),
+    ToggleQuantize,
+    NumColorsChanged(String),
+    LuminanceCutoffChanged(String),
+    ProcessFolder,
+    BatchFileDone(String, Result<usize, String>),
+    RunFullConversion(u64),
+}
+
+/// Enumerate the supported images (png/jpg/jpeg) directly inside `dir`.
+fn collect_images(dir: &PathBuf) -> Vec<PathBuf> {
+    let mut images = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(|ext| {
+                        let ext = ext.to_ascii_lowercase();
+                        ext == "png" || ext == "jpg" || ext == "jpeg"
+                    })
+                    .unwrap_or(false)
+            })
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            eprintln!("Failed to read folder: {}", e);
+            Vec::new()
+        }
+    };
+    images.sort();
+    images
+}
+
+/// Translate a key press into a [`Message`], or `None` if it is not bound.
+///
+/// * `Ctrl+O` — open the file dialog
+/// * `Ctrl+S` — export the converted image
+/// * `Escape` / `Ctrl+0` — reset the black point to 0
+fn hotkey(key_code: keyboard::KeyCode, modifiers: keyboard::Modifiers) -> Option<Message> {
+    use keyboard::KeyCode;
+
+    match key_code {
+        KeyCode::O if modifiers.control => Some(Message::FileButtonpressed),
+        KeyCode::S if modifiers.control => Some(Message::ExportFile),
+        KeyCode::Escape => Some(Message::BlackPointChanged(0)),
+        KeyCode::Key0 if modifiers.control => Some(Message::BlackPointChanged(0)),
+        _ => None,
+    }
 }
 
 impl Amoled {
-    fn handle_image_change(&mut self) {
-        if let Some(path) = &self.path_input_value {
+    /// The conversion mode described by the current UI controls.
+    fn current_mode(&self) -> ConversionMode {
+        if self.quantize_enabled {
+            ConversionMode::Quantize {
+                num_colors: self.num_colors,
+                luminance_cutoff: self.luminance_cutoff,
+            }
+        } else {
+            ConversionMode::Threshold
+        }
+    }
+
+    /// Apply the current black point + mode to the loaded image: refresh the
+    /// cheap preview on the UI thread and, on a cache miss, fire the full-res
+    /// pass as a trailing command.
+    fn reconvert(&mut self) -> Command<Message> {
+        let bp = self.black_point;
+        let mode = self.current_mode();
+        if let Some(img) = self.image.as_mut() {
+            img.set_mode(mode);
+            // Push the current black point through so the preview thumbnail and
+            // its readout track the slider (not just the load-time value).
+            img.set_black_point(bp);
+            if img.restore_cached_conversion(bp) {
+                return Command::none();
+            }
+        } else {
+            return Command::none();
+        }
+
+        // Debounce the expensive full-res pass: a fresh generation invalidates
+        // any in-flight timer, so only the last value in a scrub is converted.
+        self.conversion_generation = self.conversion_generation.wrapping_add(1);
+        let generation = self.conversion_generation;
+        Command::perform(smol::Timer::after(CONVERSION_DEBOUNCE), move |_| {
+            Message::RunFullConversion(generation)
+        })
+    }
+
+    /// Kick off an off-thread decode + full-resolution conversion for the
+    /// currently selected path. The result comes back as [`Message::ImageLoaded`].
+    fn handle_image_change(&mut self) -> Command<Message> {
+        if let Some(path) = self.path_input_value.clone() {
             println!("making new image");
-            self.image = AmoledImageConverter::from_path(&path, self.black_point).ok();
+            let black_point = self.black_point;
+            Command::perform(
+                smol::unblock(move || {
+                    AmoledImageConverter::from_path(&path, black_point).map_err(|e| e.to_string())
+                }),
+                Message::ImageLoaded,
+            )
+        } else {
+            Command::none()
         }
     }
 }
 
-impl Sandbox for Amoled {
+impl Application for Amoled {
+    type Executor = executor::Default;
     type Message = Message;
+    type Flags = ();
+
+    fn new(_flags: ()) -> (Amoled, Command<Message>) {
+        (
+            Amoled {
+                num_colors: DEFAULT_NUM_COLORS,
+                luminance_cutoff: DEFAULT_LUMINANCE_CUTOFF,
+                ..Amoled::default()
+            },
+            Command::none(),
+        )
+    }
 
-    fn new() -> Amoled {
-        Amoled::default()
+    fn subscription(&self) -> Subscription<Message> {
+        iced::subscription::events_with(|event, _status| match event {
+            iced::Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code,
+                modifiers,
+            }) => hotkey(key_code, modifiers),
+            // Scrolling zooms both previews together via the shared scale.
+            iced::Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                let steps = match delta {
+                    mouse::ScrollDelta::Lines { y, .. } => y,
+                    mouse::ScrollDelta::Pixels { y, .. } => y,
+                };
+                Some(Message::Zoom(steps))
+            }
+            _ => None,
+        })
     }
 
     fn title(&self) -> String {
@@ -81,18 +232,15 @@ impl Sandbox for Amoled {
         }
     }
 
-    fn update(&mut self, message: Self::Message) {
+    fn update(&mut self, message: Self::Message) -> Command<Message> {
         match message {
             Message::BlackPointChanged(bp) => {
                 self.black_point = bp;
-
-                if let Some(img) = self.image.as_mut() {
-                    img.set_black_point(bp);
-                }
+                self.reconvert()
             }
             Message::PathChanged(path_string) => {
                 self.path_input_value = Some(PathBuf::from(path_string));
-                self.handle_image_change();
+                self.handle_image_change()
             }
             // Message::FileCreated => todo!(),
             Message::FileButtonpressed => {
@@ -103,14 +251,168 @@ impl Sandbox for Amoled {
                     Some(new_path) => Some(new_path),
                     None => self.path_input_value.to_owned(),
                 };
-                self.handle_image_change();
+                self.handle_image_change()
+            }
+            Message::ExportFile => {
+                if let Some(img) = self.image.as_ref() {
+                    if let Some(path) = FileDialog::new()
+                        .add_filter("image", &["png", "jpg", "jpeg"])
+                        .save_file()
+                    {
+                        if let Err(e) = img.export(&path) {
+                            eprintln!("Failed to export image: {}", e);
+                        }
+                    }
+                }
+                Command::none()
+            }
+            Message::ImageLoaded(result) => match result {
+                Ok(image) => {
+                    self.image = Some(image);
+                    // Bring the freshly loaded image in line with the current
+                    // mode/black-point selection.
+                    self.reconvert()
+                }
+                Err(e) => {
+                    eprintln!("Failed to load image: {}", e);
+                    Command::none()
+                }
+            },
+            Message::RunFullConversion(generation) => {
+                // Ignore stale timers; only the most recent scrub value runs.
+                if generation != self.conversion_generation {
+                    return Command::none();
+                }
+                let bp = self.black_point;
+                let mode = self.current_mode();
+                if let Some(img) = self.image.as_ref() {
+                    let source = img.source();
+                    return Command::perform(
+                        smol::unblock(move || {
+                            let (converted, percentage) =
+                                AmoledImageConverter::convert_full(source, bp, mode);
+                            (bp, converted, percentage)
+                        }),
+                        |(bp, converted, percentage)| {
+                            Message::ConversionDone(bp, converted, percentage)
+                        },
+                    );
+                }
+                Command::none()
+            }
+            Message::ConversionDone(bp, converted, percentage) => {
+                if let Some(img) = self.image.as_mut() {
+                    img.set_converted_image(bp, converted, percentage);
+                }
+                Command::none()
+            }
+            Message::ResetView => {
+                if let Some(img) = self.image.as_mut() {
+                    img.reset_view();
+                }
+                Command::none()
+            }
+            Message::Zoom(steps) => {
+                if let Some(img) = self.image.as_mut() {
+                    img.zoom(steps);
+                }
+                Command::none()
+            }
+            Message::ToggleQuantize => {
+                self.quantize_enabled = !self.quantize_enabled;
+                self.reconvert()
+            }
+            Message::NumColorsChanged(value) => {
+                // Clamp to exoquant's supported palette range on a valid entry.
+                if let Ok(n) = value.parse::<usize>() {
+                    self.num_colors = n.max(1).min(256);
+                    return self.reconvert();
+                }
+                Command::none()
+            }
+            Message::LuminanceCutoffChanged(value) => {
+                if value.is_empty() {
+                    self.luminance_cutoff = 0;
+                    return self.reconvert();
+                }
+                if let Ok(cutoff) = value.parse::<u8>() {
+                    self.luminance_cutoff = cutoff;
+                    return self.reconvert();
+                }
+                Command::none()
+            }
+            Message::ProcessFolder => {
+                let input_dir = FileDialog::new().set_title("Select folder to convert").pick_folder();
+                let output_dir = FileDialog::new()
+                    .set_title("Select output folder")
+                    .pick_folder();
+                if let (Some(input_dir), Some(output_dir)) = (input_dir, output_dir) {
+                    let files = collect_images(&input_dir);
+                    self.batch_total = files.len();
+                    self.batch_done = 0;
+                    self.batch_success = 0;
+                    self.batch_percentage_sum = 0;
+                    self.batch_summary.clear();
+
+                    let bp = self.black_point;
+                    let mode = self.current_mode();
+                    // One off-thread job per file so the UI keeps ticking.
+                    let commands: Vec<Command<Message>> = files
+                        .into_iter()
+                        .map(|file| {
+                            let output_dir = output_dir.clone();
+                            let name = file
+                                .file_name()
+                                .and_then(|n| n.to_str())
+                                .unwrap_or("")
+                                .to_string();
+                            Command::perform(
+                                smol::unblock(move || {
+                                    AmoledImageConverter::convert_file_to_dir(
+                                        &file,
+                                        &output_dir,
+                                        bp,
+                                        mode,
+                                    )
+                                    .map(|outcome| outcome.black_pixel_percentage)
+                                    .map_err(|e| e.to_string())
+                                }),
+                                move |result| Message::BatchFileDone(name.clone(), result),
+                            )
+                        })
+                        .collect();
+                    return Command::batch(commands);
+                }
+                Command::none()
+            }
+            Message::BatchFileDone(name, result) => {
+                self.batch_done += 1;
+                match result {
+                    Ok(percentage) => {
+                        self.batch_success += 1;
+                        self.batch_percentage_sum += percentage;
+                        self.batch_summary
+                            .push(format!("{}: {}% black", name, percentage));
+                    }
+                    Err(e) => self.batch_summary.push(format!("{}: error - {}", name, e)),
+                }
+                if self.batch_total > 0 && self.batch_done == self.batch_total {
+                    let average = self.batch_percentage_sum / self.batch_success.max(1);
+                    self.batch_summary.push(format!(
+                        "Done: {}/{} converted, ~{}% average black",
+                        self.batch_success, self.batch_total, average
+                    ));
+                }
+                Command::none()
             }
             Message::BlackPointInputChanged(bp_string) => {
                 // Only update the text input if it is a u8 value
                 if bp_string.eq("") {
-                    self.update(Message::BlackPointChanged(0));
+                    self.update(Message::BlackPointChanged(0))
                 } else if let Ok(bp) = bp_string.parse::<u8>() {
-                    self.update(Message::BlackPointChanged(bp));
+                    self.update(Message::BlackPointChanged(bp))
+                } else {
+                    Command::none()
                 }
             }
         }
@@ -144,7 +446,59 @@ impl Sandbox for Amoled {
                     .padding(10)
                     .on_press(Message::FileButtonpressed),
             )
-            .max_width(500);
+            .push(
+                Button::new(&mut self.batch_button, Text::new("Process folder"))
+                    .padding(10)
+                    .on_press(Message::ProcessFolder),
+            );
+
+        // Only offer the save button once there is a converted image to write.
+        let path_input = if self.image.is_some() {
+            path_input.push(
+                Button::new(&mut self.file_save_button, Text::new("Save"))
+                    .padding(10)
+                    .on_press(Message::ExportFile),
+            )
+        } else {
+            path_input
+        }
+        .max_width(500);
+
+        let mode_label = if self.quantize_enabled {
+            "Mode: Palette reduction"
+        } else {
+            "Mode: Threshold"
+        };
+        let mut mode_row = Row::new().spacing(20).padding(5).align_items(Align::Center).push(
+            Button::new(&mut self.mode_toggle_button, Text::new(mode_label))
+                .padding(10)
+                .on_press(Message::ToggleQuantize),
+        );
+        if self.quantize_enabled {
+            mode_row = mode_row
+                .push(Text::new("Colors"))
+                .push(
+                    TextInput::new(
+                        &mut self.num_colors_input,
+                        "64",
+                        &self.num_colors.to_string(),
+                        Message::NumColorsChanged,
+                    )
+                    .width(Length::Units(50))
+                    .size(20),
+                )
+                .push(Text::new("Cutoff"))
+                .push(
+                    TextInput::new(
+                        &mut self.luminance_cutoff_input,
+                        "32",
+                        &self.luminance_cutoff.to_string(),
+                        Message::LuminanceCutoffChanged,
+                    )
+                    .width(Length::Units(50))
+                    .size(20),
+                );
+        }
 
         let top_container = Container::new(
             Column::new()
@@ -176,12 +530,41 @@ impl Sandbox for Amoled {
                         .spacing(20)
                         .padding(5),
                 )
+                .push(mode_row)
+                .push(
+                    Text::new("Shortcuts: Ctrl+O open · Ctrl+S save · Esc / Ctrl+0 reset")
+                        .size(14)
+                        .color([0.5, 0.5, 0.5])
+                        .horizontal_alignment(HorizontalAlignment::Center),
+                )
                 .align_items(Align::Center),
         );
 
-        let content = Column::new().push(top_container).align_items(Align::Center);
+        let mut content = Column::new().push(top_container).align_items(Align::Center);
 
-        if let Some(img) = self.image.as_mut() {
+        if self.batch_total > 0 {
+            let mut batch = Column::new()
+                .spacing(4)
+                .align_items(Align::Center)
+                .push(Text::new(format!(
+                    "Processed {}/{} files",
+                    self.batch_done, self.batch_total
+                )));
+            // Show the most recent results so the panel does not grow unbounded.
+            for line in self.batch_summary.iter().rev().take(10).rev() {
+                batch = batch.push(Text::new(line.clone()).size(14));
+            }
+            content = content.push(Container::new(batch).padding(10));
+        }
+
+        if self.image.is_some() {
+            let reset_view = Container::new(
+                Button::new(&mut self.reset_view_button, Text::new("Reset view"))
+                    .padding(10)
+                    .on_press(Message::ResetView),
+            );
+            let content = content.push(reset_view);
+            let img = self.image.as_mut().unwrap();
             let content = content.push(img.view());
             return Container::new(content)
                 .width(Length::Fill)
@@ -199,3 +582,29 @@ impl Sandbox for Amoled {
             .into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collect_images_keeps_only_supported_extensions() {
+        let dir = std::env::temp_dir().join(format!("amoled_collect_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["a.png", "b.jpg", "c.jpeg", "d.JPG", "e.txt", "f"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let images = collect_images(&dir);
+        let names: Vec<String> = images
+            .iter()
+            .filter_map(|p| p.file_name().and_then(|n| n.to_str()).map(String::from))
+            .collect();
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        // png/jpg/jpeg files (case-insensitive), sorted; other extensions and
+        // extension-less files are excluded.
+        assert_eq!(names, vec!["a.png", "b.jpg", "c.jpeg", "d.JPG"]);
+    }
+}